@@ -0,0 +1,145 @@
+use std::rc::Rc;
+
+use crate::matcher::RegexPattern;
+
+/// Translates a shell glob pattern into the same `RegexPattern` token stream
+/// the regex engine already knows how to run, following the substitution
+/// table Mercurial uses in its `filepatterns.rs`:
+/// - `*`   -> any run of non-separator characters (`[^/]*`)
+/// - `**`  -> any number of path segments, including none (`(?:.*/)?`)
+/// - `*/`  -> exactly one arbitrarily-named segment then a separator (`[^/]*/`)
+/// - `?`   -> a single any-character
+/// - `[...]`, `[!...]` -> positive/negative character sets
+///
+/// Every other character is pushed as a literal `Char` token, so there is no
+/// separate "escape the metacharacters" step: unlike a textual regex
+/// translator, this builds typed tokens directly, and a `Char('.')` can
+/// never be reinterpreted downstream as "any character".
+pub fn translate(glob: &str) -> Vec<RegexPattern> {
+    let mut chars = glob.chars().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                    }
+                    tokens.push(any_path_segments());
+                } else if chars.peek() == Some(&'/') {
+                    chars.next();
+                    tokens.push(RegexPattern::Star(Box::new(RegexPattern::NegativeCharSet(
+                        vec!['/'],
+                    ))));
+                    tokens.push(RegexPattern::Char('/'));
+                } else {
+                    tokens.push(RegexPattern::Star(Box::new(RegexPattern::NegativeCharSet(
+                        vec!['/'],
+                    ))));
+                }
+            }
+            '?' => tokens.push(RegexPattern::Dot),
+            '[' => tokens.push(parse_char_set(&mut chars, glob)),
+            other => tokens.push(RegexPattern::Char(other)),
+        }
+    }
+
+    tokens
+}
+
+/// `(?:.*/)?` as a token: either "any characters followed by a separator",
+/// or nothing at all. Modeled as an `Alternative` between that sequence and
+/// an empty one, since `Alternative` is already how this engine expresses
+/// "try branch A, else fall through to branch B".
+fn any_path_segments() -> RegexPattern {
+    RegexPattern::Alternative(
+        Rc::new(Box::new(vec![
+            RegexPattern::Star(Box::new(RegexPattern::Dot)),
+            RegexPattern::Char('/'),
+        ])),
+        Rc::new(Box::new(Vec::new())),
+    )
+}
+
+fn parse_char_set(chars: &mut std::iter::Peekable<std::str::Chars>, glob: &str) -> RegexPattern {
+    let mut set = Vec::new();
+    let mut negative = false;
+
+    if chars.peek() == Some(&'!') {
+        negative = true;
+        chars.next();
+    }
+
+    loop {
+        match chars.next() {
+            Some(']') => break,
+            Some(c) => set.push(c),
+            None => panic!("Unterminated character set in glob: {:?}", glob),
+        }
+    }
+
+    if negative {
+        RegexPattern::NegativeCharSet(set)
+    } else {
+        RegexPattern::PositiveCharSet(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::matcher::match_tokens;
+
+    fn matches(glob: &str, input: &str) -> bool {
+        match_tokens(input, &translate(glob))
+    }
+
+    #[test]
+    fn star_stops_at_a_path_separator() {
+        assert!(matches("*.txt", "notes.txt"));
+        assert!(!matches("*.txt", "a/notes.txt"));
+        assert!(!matches("*.txt", "notes.txtx"));
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_path_segments() {
+        assert!(matches("**/*.rs", "foo.rs"));
+        assert!(matches("**/*.rs", "a/foo.rs"));
+        assert!(matches("**/*.rs", "a/b/foo.rs"));
+        assert!(!matches("**/*.rs", "a/foo.txt"));
+    }
+
+    #[test]
+    fn lone_star_slash_requires_exactly_one_segment() {
+        assert!(matches("*/*.rs", "a/foo.rs"));
+        assert!(!matches("*/*.rs", "foo.rs"));
+        assert!(!matches("*/*.rs", "a/b/foo.rs"));
+    }
+
+    #[test]
+    fn question_mark_matches_a_single_character() {
+        assert!(matches("fil?.txt", "file.txt"));
+        assert!(!matches("fil?.txt", "fil.txt"));
+        assert!(!matches("fil?.txt", "fileee.txt"));
+    }
+
+    #[test]
+    fn char_sets_support_positive_and_negative_forms() {
+        assert!(matches("file.[ch]", "file.c"));
+        assert!(matches("file.[ch]", "file.h"));
+        assert!(!matches("file.[ch]", "file.o"));
+
+        assert!(matches("file.[!ch]", "file.o"));
+        assert!(!matches("file.[!ch]", "file.c"));
+    }
+
+    #[test]
+    fn translated_tokens_require_a_full_string_match() {
+        // Regression: match_tokens used to run the unanchored substring
+        // search, so a glob could match in the middle of a longer string.
+        assert!(!matches("*.txt", "notes.txt.bak"));
+        assert!(!matches("foo", "xfoo"));
+    }
+}
@@ -1,33 +1,230 @@
+mod glob;
 mod matcher;
 
 use std::env;
-use std::io;
+use std::fs;
+use std::io::{self, BufRead};
 use std::process;
 
-use matcher::matcher::match_pattern;
-
-// fn match_pattern(input_line: &str, pattern: &str) -> bool {
-//     if pattern.chars().count() == 1 {
-//         return input_line.contains(pattern);
-//     } else {
-//         panic!("Unhandled pattern: {}", pattern)
-//     }
-// }
+use matcher::matcher::{
+    match_any, match_any_unicode, match_pattern, match_pattern_bytes, match_pattern_unicode, match_tokens,
+};
 
 fn main() {
-    if env::args().nth(1).unwrap() != "-E" {
-        println!("Expected first argument to be '-E'");
+    let mut args: Vec<String> = env::args().collect();
+    let unicode = take_flag(&mut args, &["-u", "--unicode"]);
+    let bytes = take_flag(&mut args, &["--bytes"]);
+
+    match args.get(1).map(String::as_str) {
+        Some("-g") | Some("--glob") => {
+            let glob_pattern = args.get(2).unwrap_or_else(|| {
+                println!("Expected a glob pattern to follow '{}'", args[1]);
+                process::exit(1);
+            });
+            let tokens = glob::translate(glob_pattern);
+
+            if match_tokens(&read_input_line(), &tokens) {
+                process::exit(0)
+            } else {
+                process::exit(1)
+            }
+        }
+        Some("-f") => {
+            let path = args.get(2).unwrap_or_else(|| {
+                println!("Expected a file path to follow '-f'");
+                process::exit(1);
+            });
+            let patterns = read_pattern_file(path);
+            let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+            let input_line = read_input_line();
+
+            let matched = if unicode {
+                !match_any_unicode(&input_line, &patterns).is_empty()
+            } else {
+                !match_any(&input_line, &patterns).is_empty()
+            };
+
+            if matched {
+                process::exit(0)
+            } else {
+                process::exit(1)
+            }
+        }
+        _ => {
+            let patterns = parse_e_flags(&args);
+
+            let matched = if bytes {
+                let input = read_input_bytes();
+                patterns.iter().any(|pattern| match_pattern_bytes(&input, pattern))
+            } else {
+                let input_line = read_input_line();
+                if let [pattern] = patterns.as_slice() {
+                    if unicode {
+                        match_pattern_unicode(&input_line, pattern)
+                    } else {
+                        match_pattern(&input_line, pattern)
+                    }
+                } else {
+                    let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+                    if unicode {
+                        !match_any_unicode(&input_line, &patterns).is_empty()
+                    } else {
+                        !match_any(&input_line, &patterns).is_empty()
+                    }
+                }
+            };
+
+            if matched {
+                process::exit(0)
+            } else {
+                process::exit(1)
+            }
+        }
+    }
+}
+
+/// Mode-selecting flags. Once one of these is seen, everything after it
+/// belongs to that mode (a pattern, a glob, a file path, ...) and must not
+/// be scanned for top-level flags like `-u`/`--bytes` — otherwise a pattern
+/// that's literally `-u` or `--bytes` would be silently eaten.
+const MODE_FLAGS: &[&str] = &["-E", "-g", "--glob", "-f"];
+
+/// Removes the first argument matching any of `names` from `args` (if
+/// present) and reports whether it was found. Only scans the leading
+/// arguments before the first mode flag, so flags like `-u` can be accepted
+/// anywhere ahead of the mode-specific arguments without ever touching
+/// values that belong to that mode.
+fn take_flag(args: &mut Vec<String>, names: &[&str]) -> bool {
+    let mut i = 1;
+    while i < args.len() {
+        if names.contains(&args[i].as_str()) {
+            args.remove(i);
+            return true;
+        }
+        if MODE_FLAGS.contains(&args[i].as_str()) {
+            break;
+        }
+        i += 1;
+    }
+    false
+}
+
+fn parse_e_flags(args: &[String]) -> Vec<String> {
+    let mut patterns: Vec<String> = Vec::new();
+
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] != "-E" {
+            println!("Expected argument to be '-E'");
+            process::exit(1);
+        }
+        i += 1;
+        match args.get(i) {
+            Some(pattern) => patterns.push(pattern.clone()),
+            None => {
+                println!("Expected a pattern to follow '-E'");
+                process::exit(1);
+            }
+        }
+        i += 1;
+    }
+
+    if patterns.is_empty() {
+        println!("Expected at least one '-E' argument");
+        process::exit(1);
+    }
+
+    patterns
+}
+
+/// Reads one line from stdin as raw bytes first, rather than `read_line`ing
+/// straight into a `String`, so invalid UTF-8 input is reported cleanly
+/// instead of panicking partway through decoding.
+fn read_input_line() -> String {
+    let mut buf: Vec<u8> = Vec::new();
+    io::stdin().lock().read_until(b'\n', &mut buf).unwrap_or_else(|err| {
+        println!("Failed to read from stdin: {}", err);
         process::exit(1);
+    });
+
+    while matches!(buf.last(), Some(b'\n') | Some(b'\r')) {
+        buf.pop();
+    }
+
+    String::from_utf8(buf).unwrap_or_else(|_| {
+        println!("Input is not valid UTF-8");
+        process::exit(1);
+    })
+}
+
+/// Reads one line from stdin as raw bytes, without requiring valid UTF-8,
+/// for `--bytes` mode.
+fn read_input_bytes() -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    io::stdin().lock().read_until(b'\n', &mut buf).unwrap_or_else(|err| {
+        println!("Failed to read from stdin: {}", err);
+        process::exit(1);
+    });
+
+    while matches!(buf.last(), Some(b'\n') | Some(b'\r')) {
+        buf.pop();
+    }
+
+    buf
+}
+
+/// Loads `path` and treats each non-empty, non-`#`-comment line as a
+/// separate pattern, following the `readpatternfile` utility from
+/// Mercurial's `filepatterns.rs`.
+fn read_pattern_file(path: &str) -> Vec<String> {
+    let contents = fs::read_to_string(path).unwrap_or_else(|err| {
+        println!("Could not read pattern file '{}': {}", path, err);
+        process::exit(1);
+    });
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a fresh path under the system temp dir (unique
+    /// per run via the process id and per test via `name`, since there's no
+    /// fixture crate here)
+    /// and returns the path for `read_pattern_file` to load.
+    fn write_pattern_file(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!("grep-rust-test-{}-{}", process::id(), name));
+        fs::write(&path, contents).expect("failed to write test fixture");
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn read_pattern_file_skips_blank_lines_and_comments() {
+        let path = write_pattern_file(
+            "filter",
+            "cat\n\n# this is a comment\n  \ndog\n   # indented comment\nbird\n",
+        );
+
+        assert_eq!(read_pattern_file(&path), vec!["cat", "dog", "bird"]);
+
+        fs::remove_file(&path).unwrap();
     }
 
-    let pattern = env::args().nth(2).unwrap();
-    let mut input_line = String::new();
+    #[test]
+    fn f_mode_matches_if_any_pattern_in_the_file_matches() {
+        let path = write_pattern_file("match", "cat\n# bird\ndog\n");
+        let patterns = read_pattern_file(&path);
+        let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
 
-    io::stdin().read_line(&mut input_line).unwrap();
+        assert!(!match_any("a dog ran", &patterns).is_empty());
+        assert!(match_any("a fish swam", &patterns).is_empty());
 
-    if match_pattern(&input_line.trim_end(), &pattern) {
-        process::exit(0)
-    } else {
-        process::exit(1)
+        fs::remove_file(&path).unwrap();
     }
 }
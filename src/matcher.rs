@@ -9,10 +9,13 @@ pub enum RegexPattern {
     NegativeCharSet(Vec<char>),                                          // [^abc]
     Start,                                                               // ^
     End,                                                                 // $
-    Plus(char),                                                          // +
-    ZeroOrOne(char),                                                     // ?
+    Plus(Box<RegexPattern>),                                             // +
+    ZeroOrOne(Box<RegexPattern>),                                        // ?
+    Star(Box<RegexPattern>),                                             // *
+    Repeat(Box<RegexPattern>, usize, Option<usize>),                     // {n}, {n,}, {n,m}
     Dot,                                                                 // .
     Alternative(Rc<Box<Vec<RegexPattern>>>, Rc<Box<Vec<RegexPattern>>>), // (a|b)
+    Byte(u8),                                                            // \xNN
 }
 
 pub mod matcher {
@@ -22,17 +25,71 @@ pub mod matcher {
     use super::RegexPattern;
 
     pub fn match_pattern(input_line: &str, pattern: &str) -> bool {
-        if pattern.starts_with('^') && input_line.starts_with(&pattern[1..]) {
-            return true;
-        }
+        !match_any(input_line, &[pattern]).is_empty()
+    }
 
-        if pattern.ends_with('$') && input_line.ends_with(&pattern[..pattern.len() - 1]) {
-            return true;
-        }
+    /// Like `match_pattern`, but `\w`/`\d` match Unicode alphanumeric/numeric
+    /// characters instead of only the ASCII ranges.
+    pub fn match_pattern_unicode(input_line: &str, pattern: &str) -> bool {
+        !match_any_with(input_line, &[pattern], true).is_empty()
+    }
+
+    /// Matches `input_line` against an already-parsed token stream, e.g. one
+    /// produced by the `glob` module, bypassing `parse_pattern` entirely.
+    /// Unlike `match_pattern`/`match_any`, this is a full-string match: the
+    /// tokens are wrapped in implicit `Start`/`End` anchors so translated
+    /// globs can't be satisfied by matching some substring in the middle
+    /// (which would defeat e.g. `[^/]*` never being allowed to cross a `/`).
+    pub fn match_tokens(input_line: &str, tokens: &[RegexPattern]) -> bool {
+        let input: Vec<char> = input_line.chars().collect();
+
+        let mut anchored = Vec::with_capacity(tokens.len() + 2);
+        anchored.push(RegexPattern::Start);
+        anchored.extend_from_slice(tokens);
+        anchored.push(RegexPattern::End);
 
-        let regex_pattern = parse_pattern(pattern);
+        let program = compile(&anchored, 0);
+        !run(&program, &[0], 1, &input, false).is_empty()
+    }
+
+    /// Matches `input_line` against every expression in `patterns` in a
+    /// single linear scan and returns the indices of the ones that matched,
+    /// rather than requiring one `match_pattern` call per expression.
+    pub fn match_any(input_line: &str, patterns: &[&str]) -> Vec<usize> {
+        match_any_with(input_line, patterns, false)
+    }
+
+    /// Like `match_any`, but `\w`/`\d` match Unicode alphanumeric/numeric
+    /// characters instead of only the ASCII ranges.
+    pub fn match_any_unicode(input_line: &str, patterns: &[&str]) -> Vec<usize> {
+        match_any_with(input_line, patterns, true)
+    }
 
-        match_with_pattern(input_line, &regex_pattern)
+    /// Matches raw bytes instead of a `&str`, for input that isn't
+    /// guaranteed to be valid UTF-8 (binary files, arbitrary byte streams).
+    /// `.`, character classes, and `\d`/`\w` test the byte value directly;
+    /// literal bytes outside the ASCII range can be specified with `\xNN`.
+    pub fn match_pattern_bytes(input: &[u8], pattern: &str) -> bool {
+        let tokens = parse_pattern(pattern);
+        let program = compile(&tokens, 0);
+        !run_bytes(&program, &[0], 1, input).is_empty()
+    }
+
+    fn match_any_with(input_line: &str, patterns: &[&str], unicode: bool) -> Vec<usize> {
+        let input: Vec<char> = input_line.chars().collect();
+
+        let mut program: Vec<Inst> = Vec::new();
+        let mut starts: Vec<usize> = Vec::with_capacity(patterns.len());
+
+        for (id, pattern) in patterns.iter().enumerate() {
+            let tokens = parse_pattern(pattern);
+            let frag = compile(&tokens, id);
+            let base = program.len();
+            starts.push(base);
+            program.extend(frag.into_iter().map(|inst| shift(inst, base)));
+        }
+
+        run(&program, &starts, patterns.len(), &input, unicode)
     }
 
     fn parse_pattern(pattern: &str) -> Vec<RegexPattern> {
@@ -51,36 +108,41 @@ pub mod matcher {
                     Some('w') => {
                         tokens.push(RegexPattern::Word);
                     }
-                    _ => panic!("Unhandled escape sequence: \\{:?}", pattern),
-                },
-                Some('+') => {
-                    if tokens.len() == 0 {
-                        // normal character
-                        tokens.push(RegexPattern::Char('+'));
-                    } else {
-                        // get the last token
-                        let last_token = tokens.pop().expect("No token to apply + operator to");
-                        match last_token {
-                            RegexPattern::Char(c) => {
-                                tokens.push(RegexPattern::Plus(c));
+                    Some('x') => {
+                        let hi = pattern_chars.next().and_then(|c| c.to_digit(16));
+                        let lo = pattern_chars.next().and_then(|c| c.to_digit(16));
+                        match (hi, lo) {
+                            (Some(hi), Some(lo)) => {
+                                tokens.push(RegexPattern::Byte((hi * 16 + lo) as u8));
                             }
-                            _ => panic!("Unhandled + operator: {:?}", pattern),
+                            _ => panic!("Invalid \\x escape in: {:?}", pattern),
                         }
                     }
+                    _ => panic!("Unhandled escape sequence: \\{:?}", pattern),
+                },
+                Some('+') => {
+                    apply_quantifier(&mut tokens, pattern, RegexPattern::Plus);
                 }
                 Some('?') => {
-                    if tokens.len() == 0 {
-                        // normal character
-                        tokens.push(RegexPattern::Char('?'));
-                    } else {
-                        // get the last token
-                        let last_token = tokens.pop().expect("No token to apply ? operator to");
-                        match last_token {
-                            RegexPattern::Char(c) => {
-                                tokens.push(RegexPattern::ZeroOrOne(c));
+                    apply_quantifier(&mut tokens, pattern, RegexPattern::ZeroOrOne);
+                }
+                Some('*') => {
+                    apply_quantifier(&mut tokens, pattern, RegexPattern::Star);
+                }
+                Some('{') => {
+                    let rest = pattern_chars.as_str();
+                    match parse_counted_repetition(rest) {
+                        Some((min, max, consumed)) => {
+                            for _ in 0..consumed {
+                                pattern_chars.next();
                             }
-                            _ => panic!("Unhandled ? operator: {:?}", pattern),
+                            apply_quantifier(&mut tokens, pattern, |inner| {
+                                RegexPattern::Repeat(inner, min, max)
+                            });
                         }
+                        // Not a well-formed `{n}`/`{n,}`/`{n,m}`: treat `{` as a literal character,
+                        // matching how real grep/regex engines degrade gracefully.
+                        None => tokens.push(RegexPattern::Char('{')),
                     }
                 }
                 Some('.') => {
@@ -92,13 +154,10 @@ pub mod matcher {
                         tokens.push(RegexPattern::Char('^'));
                     } else {
                         tokens.push(RegexPattern::Start);
-                        // if it is line anchor, we don't need to parse the rest of the pattern
-                        return tokens;
                     }
                 }
                 Some('$') => {
                     tokens.push(RegexPattern::End);
-                    return tokens;
                 }
                 Some('[') => {
                     let mut char_set = Vec::new();
@@ -159,9 +218,6 @@ pub mod matcher {
                         }
                     }
 
-                    // let pattern_a = parse_pattern(&pattern_a);
-                    // let pattern_b = parse_pattern(&pattern_b);
-
                     tokens.push(RegexPattern::Alternative(
                         Rc::new(Box::new(parse_pattern(&pattern_a))),
                         Rc::new(Box::new(parse_pattern(&pattern_b))),
@@ -177,140 +233,537 @@ pub mod matcher {
         }
     }
 
-    fn match_with_pattern(input_line: &str, pattern: &[RegexPattern]) -> bool {
-        let mut input_bytes = input_line.as_bytes();
-        let mut pattern_iter = pattern.iter().peekable();
+    /// Pops the token a `+`/`?`/`*`/`{n,m}` suffix applies to and wraps it, matching the
+    /// real grep behaviour of quantifying whatever came right before the
+    /// operator (a char, a class, `.`, or a group) instead of only a bare char.
+    fn apply_quantifier(
+        tokens: &mut Vec<RegexPattern>,
+        pattern: &str,
+        wrap: impl FnOnce(Box<RegexPattern>) -> RegexPattern,
+    ) {
+        match tokens.pop() {
+            None => panic!("No token to apply quantifier to in: {:?}", pattern),
+            Some(RegexPattern::Start) | Some(RegexPattern::End) => {
+                panic!("Cannot quantify an anchor in: {:?}", pattern)
+            }
+            Some(RegexPattern::Plus(_))
+            | Some(RegexPattern::ZeroOrOne(_))
+            | Some(RegexPattern::Star(_))
+            | Some(RegexPattern::Repeat(..)) => {
+                panic!("Cannot stack quantifiers in: {:?}", pattern)
+            }
+            Some(last_token) => tokens.push(wrap(Box::new(last_token))),
+        }
+    }
+
+    /// Parses a `{n}`, `{n,}`, or `{n,m}` counted repetition from `rest` (the
+    /// pattern text right after the `{`). Returns `(min, max, chars_consumed)`
+    /// where `chars_consumed` does not include the `{` itself but does
+    /// include the trailing `}`. Returns `None` if `rest` isn't a well-formed
+    /// counted repetition, so the caller can fall back to a literal `{`.
+    fn parse_counted_repetition(rest: &str) -> Option<(usize, Option<usize>, usize)> {
+        let close = rest.find('}')?;
+        let body = &rest[..close];
+        let consumed = close + 1;
 
-        while let Some(pat) = pattern_iter.next() {
-            match pat {
-                RegexPattern::Char(c) => {
-                    if input_bytes.first() == Some(&(*c as u8)) {
-                        input_bytes = &input_bytes[1..];
-                    } else {
-                        if let Some(&RegexPattern::ZeroOrOne(_)) = pattern_iter.peek() {
-                            input_bytes = &input_bytes[2..];
-                            pattern_iter.next(); // Skip the ZeroOrOne pattern
-                        } else {
-                            return false;
-                        }
-                    }
+        if let Some((min_str, max_str)) = body.split_once(',') {
+            let min: usize = min_str.parse().ok()?;
+            if max_str.is_empty() {
+                Some((min, None, consumed))
+            } else {
+                let max: usize = max_str.parse().ok()?;
+                Some((min, Some(max), consumed))
+            }
+        } else {
+            let n: usize = body.parse().ok()?;
+            Some((n, Some(n), consumed))
+        }
+    }
+
+    /// A single instruction in the compiled Thompson NFA program. Programs are
+    /// flat `Vec<Inst>`s; `Split`/`Jmp` targets are absolute indices into that
+    /// vector, so fragments are compiled in isolation (as if they started at
+    /// index 0) and then shifted into place when they're spliced together.
+    #[derive(Debug, Clone)]
+    enum Inst {
+        Char(char),
+        Digit,
+        Word,
+        PositiveCharSet(Vec<char>),
+        NegativeCharSet(Vec<char>),
+        Dot,
+        Start,
+        End,
+        Split(usize, usize),
+        Jmp(usize),
+        Match(usize),
+    }
+
+    fn shift(inst: Inst, base: usize) -> Inst {
+        match inst {
+            Inst::Split(x, y) => Inst::Split(x + base, y + base),
+            Inst::Jmp(x) => Inst::Jmp(x + base),
+            other => other,
+        }
+    }
+
+    fn compile_tokens(tokens: &[RegexPattern]) -> Vec<Inst> {
+        let mut prog = Vec::new();
+        for token in tokens {
+            let frag = compile_token(token);
+            let base = prog.len();
+            prog.extend(frag.into_iter().map(|inst| shift(inst, base)));
+        }
+        prog
+    }
+
+    fn compile_token(token: &RegexPattern) -> Vec<Inst> {
+        match token {
+            RegexPattern::Char(c) => vec![Inst::Char(*c)],
+            // A byte maps onto the Latin-1 range of Unicode scalar values, so
+            // the char-oriented engine can run it unchanged; the byte-mode
+            // engine compares it against a raw byte instead.
+            RegexPattern::Byte(b) => vec![Inst::Char(*b as char)],
+            RegexPattern::Digit => vec![Inst::Digit],
+            RegexPattern::Word => vec![Inst::Word],
+            RegexPattern::PositiveCharSet(set) => vec![Inst::PositiveCharSet(set.clone())],
+            RegexPattern::NegativeCharSet(set) => vec![Inst::NegativeCharSet(set.clone())],
+            RegexPattern::Dot => vec![Inst::Dot],
+            RegexPattern::Start => vec![Inst::Start],
+            RegexPattern::End => vec![Inst::End],
+            RegexPattern::Alternative(a, b) => compile_alternative(a, b),
+            RegexPattern::Plus(inner) => {
+                // L0: inner; Split(L0, end) -- loop back for one-or-more.
+                let mut prog = compile_token(inner);
+                let loop_start = 0;
+                let end = prog.len() + 1;
+                prog.push(Inst::Split(loop_start, end));
+                prog
+            }
+            RegexPattern::ZeroOrOne(inner) => {
+                // Split(body, end); body: inner; end:
+                let body = compile_token(inner);
+                let end = body.len() + 1;
+                let mut prog = Vec::with_capacity(end);
+                prog.push(Inst::Split(1, end));
+                prog.extend(body.into_iter().map(|inst| shift(inst, 1)));
+                prog
+            }
+            RegexPattern::Star(inner) => {
+                // L0: Split(L1, end); L1: inner; Jmp(L0); end:
+                let body = compile_token(inner);
+                let end = body.len() + 2;
+                let mut prog = Vec::with_capacity(end);
+                prog.push(Inst::Split(1, end));
+                prog.extend(body.into_iter().map(|inst| shift(inst, 1)));
+                prog.push(Inst::Jmp(0));
+                prog
+            }
+            RegexPattern::Repeat(inner, min, max) => compile_repeat(inner, *min, *max),
+        }
+    }
+
+    /// Lowers `{n,m}` (and `{n,}`/`{n}`) into `n` mandatory copies of `inner`
+    /// followed by either an unbounded star loop (`{n,}`) or `m - n` nested
+    /// optional copies (`{n,m}`), so a skipped optional copy can't be resumed.
+    fn compile_repeat(inner: &RegexPattern, min: usize, max: Option<usize>) -> Vec<Inst> {
+        let mut prog = Vec::new();
+        for _ in 0..min {
+            let frag = compile_token(inner);
+            let base = prog.len();
+            prog.extend(frag.into_iter().map(|inst| shift(inst, base)));
+        }
+
+        let tail = match max {
+            None => compile_token(&RegexPattern::Star(Box::new(inner.clone()))),
+            Some(max) => compile_optional_chain(inner, max.saturating_sub(min)),
+        };
+        let base = prog.len();
+        prog.extend(tail.into_iter().map(|inst| shift(inst, base)));
+        prog
+    }
+
+    /// Compiles `remaining` nested optional copies of `inner`: `inner?` that,
+    /// if taken, is itself followed by one fewer remaining optional copy.
+    fn compile_optional_chain(inner: &RegexPattern, remaining: usize) -> Vec<Inst> {
+        if remaining == 0 {
+            return Vec::new();
+        }
+
+        let body = compile_token(inner);
+        let body_len = body.len();
+        let tail = compile_optional_chain(inner, remaining - 1);
+        let end = 1 + body_len + tail.len();
+
+        let mut prog = Vec::with_capacity(end);
+        prog.push(Inst::Split(1, end));
+        prog.extend(body.into_iter().map(|inst| shift(inst, 1)));
+        prog.extend(tail.into_iter().map(|inst| shift(inst, 1 + body_len)));
+        prog
+    }
+
+    fn compile_alternative(a: &[RegexPattern], b: &[RegexPattern]) -> Vec<Inst> {
+        let prog_a = compile_tokens(a);
+        let prog_b = compile_tokens(b);
+
+        // Split(a_start, b_start); prog_a; Jmp(end); prog_b; end:
+        let a_start = 1;
+        let b_start = a_start + prog_a.len() + 1;
+        let end = b_start + prog_b.len();
+
+        let mut prog = Vec::with_capacity(end);
+        prog.push(Inst::Split(a_start, b_start));
+        prog.extend(prog_a.into_iter().map(|inst| shift(inst, a_start)));
+        prog.push(Inst::Jmp(end));
+        prog.extend(prog_b.into_iter().map(|inst| shift(inst, b_start)));
+        prog
+    }
+
+    /// Compiles `tokens` into a program ending in `Match(pattern_id)`, ready to
+    /// be spliced into a larger multi-pattern program starting at `base`.
+    fn compile(tokens: &[RegexPattern], pattern_id: usize) -> Vec<Inst> {
+        let mut prog = compile_tokens(tokens);
+        prog.push(Inst::Match(pattern_id));
+        prog
+    }
+
+    /// Adds `pc` and everything reachable from it through epsilon instructions
+    /// (`Split`, `Jmp`, and anchors) to `list`, recording consuming
+    /// instructions and `Match`. `seen` ensures each pc is only ever added
+    /// once per simulation step, which is what keeps the whole thing linear.
+    fn add_thread(
+        prog: &[Inst],
+        list: &mut Vec<usize>,
+        seen: &mut [bool],
+        pc: usize,
+        pos: usize,
+        input_len: usize,
+    ) {
+        if seen[pc] {
+            return;
+        }
+        seen[pc] = true;
+
+        match &prog[pc] {
+            Inst::Jmp(x) => add_thread(prog, list, seen, *x, pos, input_len),
+            Inst::Split(x, y) => {
+                add_thread(prog, list, seen, *x, pos, input_len);
+                add_thread(prog, list, seen, *y, pos, input_len);
+            }
+            Inst::Start => {
+                if pos == 0 {
+                    add_thread(prog, list, seen, pc + 1, pos, input_len);
                 }
-                RegexPattern::Digit => {
-                    if let Some((index, _)) = input_bytes
-                        .iter()
-                        .enumerate()
-                        .find(|(_, &b)| b.is_ascii_digit())
-                    {
-                        input_bytes = &input_bytes[index + 1..];
-                    } else {
-                        return false;
-                    }
+            }
+            Inst::End => {
+                if pos == input_len {
+                    add_thread(prog, list, seen, pc + 1, pos, input_len);
                 }
-                RegexPattern::Word => {
-                    if let Some((index, _)) = input_bytes
-                        .iter()
-                        .enumerate()
-                        .find(|(_, &b)| b.is_ascii_alphanumeric() || b == ('_' as u8))
-                    {
-                        input_bytes = &input_bytes[index + 1..];
-                    } else {
-                        return false;
+            }
+            _ => list.push(pc),
+        }
+    }
+
+    /// Runs the PikeVM simulation of `program` over `input`, starting a fresh
+    /// thread at every `starts[i]` at every input position (an unanchored
+    /// search), and returns the sorted, deduplicated set of `Match(id)`s that
+    /// fired. `num_patterns` sizes the match bitmap.
+    fn run(
+        program: &[Inst],
+        starts: &[usize],
+        num_patterns: usize,
+        input: &[char],
+        unicode: bool,
+    ) -> Vec<usize> {
+        let n = program.len();
+        let mut matched = vec![false; num_patterns];
+
+        let mut clist: Vec<usize> = Vec::new();
+        let mut seen = vec![false; n];
+        for &start in starts {
+            add_thread(program, &mut clist, &mut seen, start, 0, input.len());
+        }
+
+        for pos in 0..=input.len() {
+            let mut nlist: Vec<usize> = Vec::new();
+            let mut seen = vec![false; n];
+
+            for &pc in &clist {
+                match &program[pc] {
+                    Inst::Match(id) => matched[*id] = true,
+                    Inst::Char(c) => {
+                        if pos < input.len() && input[pos] == *c {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
                     }
-                }
-                RegexPattern::PositiveCharSet(char_set) => {
-                    // Positive character groups match any character that is present within a pair of square brackets
-                    // Example: [abc] matches any character that is either a, b, or c
-
-                    let mut matched = false;
-                    for c in char_set {
-                        if input_bytes.first() == Some(&(*c as u8)) {
-                            matched = true;
-                            break;
+                    Inst::Digit => {
+                        let is_digit = pos < input.len()
+                            && if unicode {
+                                input[pos].is_numeric()
+                            } else {
+                                input[pos].is_ascii_digit()
+                            };
+                        if is_digit {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
                         }
                     }
-
-                    if matched {
-                        input_bytes = &input_bytes[1..];
-                    } else {
-                        return false;
+                    Inst::Word => {
+                        let is_word = pos < input.len()
+                            && if unicode {
+                                input[pos].is_alphanumeric() || input[pos] == '_'
+                            } else {
+                                input[pos].is_ascii_alphanumeric() || input[pos] == '_'
+                            };
+                        if is_word {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
                     }
-                }
-                RegexPattern::NegativeCharSet(char_set) => {
-                    // Negative character groups match any character that is not present within a pair of square brackets
-                    // Example: [^abc] matches any character that is not a, b, or c
-
-                    let mut matched = false;
-                    for c in char_set {
-                        if input_bytes.first() == Some(&(*c as u8)) {
-                            matched = true;
-                            break;
+                    Inst::PositiveCharSet(set) => {
+                        if pos < input.len() && set.contains(&input[pos]) {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
                         }
                     }
-
-                    if matched {
-                        return false;
-                    } else {
-                        input_bytes = &input_bytes[1..];
+                    Inst::NegativeCharSet(set) => {
+                        if pos < input.len() && !set.contains(&input[pos]) {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
                     }
+                    Inst::Dot => {
+                        if pos < input.len() {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
+                    }
+                    Inst::Start | Inst::End | Inst::Split(..) | Inst::Jmp(_) => unreachable!(
+                        "epsilon instructions are resolved by add_thread before reaching clist"
+                    ),
                 }
-                RegexPattern::Start => {
-                    return false;
-                }
-                RegexPattern::End => {
-                    return false;
+            }
+
+            // Unanchored search: a new attempt can start at every position.
+            if pos < input.len() {
+                for &start in starts {
+                    add_thread(program, &mut nlist, &mut seen, start, pos + 1, input.len());
                 }
-                RegexPattern::Plus(c) => {
-                    // check input line for the character c
-                    if input_bytes.first() == Some(&(*c as u8)) {
-                        // if it is present, keep consuming the character
-                        input_bytes = &input_bytes[1..];
-
-                        // check if the next character is also the same
-                        while input_bytes.first() == Some(&(*c as u8)) {
-                            input_bytes = &input_bytes[1..];
+            }
+
+            clist = nlist;
+        }
+
+        let mut ids: Vec<usize> = matched
+            .iter()
+            .enumerate()
+            .filter(|(_, &m)| m)
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The byte-oriented twin of `run`: identical PikeVM simulation, but
+    /// indexing `&[u8]` and testing instructions against raw byte values
+    /// instead of `char`s. `add_thread` only needs the input length, so it's
+    /// shared verbatim between both engines.
+    fn run_bytes(program: &[Inst], starts: &[usize], num_patterns: usize, input: &[u8]) -> Vec<usize> {
+        let n = program.len();
+        let mut matched = vec![false; num_patterns];
+
+        let mut clist: Vec<usize> = Vec::new();
+        let mut seen = vec![false; n];
+        for &start in starts {
+            add_thread(program, &mut clist, &mut seen, start, 0, input.len());
+        }
+
+        for pos in 0..=input.len() {
+            let mut nlist: Vec<usize> = Vec::new();
+            let mut seen = vec![false; n];
+
+            for &pc in &clist {
+                match &program[pc] {
+                    Inst::Match(id) => matched[*id] = true,
+                    Inst::Char(c) => {
+                        if pos < input.len() && *c as u32 <= 0xFF && input[pos] == *c as u8 {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
                         }
-                    } else {
-                        return false;
                     }
-                }
-                RegexPattern::ZeroOrOne(c) => {
-                    // check input line for the character c
-                    if input_bytes.first() == Some(&(*c as u8)) {
-                        // if it is present, keep consuming the character
-                        input_bytes = &input_bytes[1..];
+                    Inst::Digit => {
+                        if pos < input.len() && input[pos].is_ascii_digit() {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
                     }
-                }
-                RegexPattern::Dot => {
-                    if input_bytes.first().is_some() {
-                        input_bytes = &input_bytes[1..];
-                    } else {
-                        return false;
+                    Inst::Word => {
+                        if pos < input.len() && (input[pos].is_ascii_alphanumeric() || input[pos] == b'_') {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
+                    }
+                    Inst::PositiveCharSet(set) => {
+                        if pos < input.len() && set.iter().any(|c| *c as u32 <= 0xFF && input[pos] == *c as u8) {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
                     }
+                    Inst::NegativeCharSet(set) => {
+                        if pos < input.len() && !set.iter().any(|c| *c as u32 <= 0xFF && input[pos] == *c as u8) {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
+                    }
+                    Inst::Dot => {
+                        if pos < input.len() {
+                            add_thread(program, &mut nlist, &mut seen, pc + 1, pos + 1, input.len());
+                        }
+                    }
+                    Inst::Start | Inst::End | Inst::Split(..) | Inst::Jmp(_) => unreachable!(
+                        "epsilon instructions are resolved by add_thread before reaching clist"
+                    ),
                 }
+            }
 
-                RegexPattern::Alternative(pattern_1, pattern_2) => {
-                    let input_bytes_1 = input_bytes;
-                    let input_bytes_2 = input_bytes;
-
-                    let pattern_1: Vec<RegexPattern> = pattern_1.as_ref().as_ref().clone();
-                    let pattern_2: Vec<RegexPattern> = pattern_2.as_ref().as_ref().clone();
-
-                    if let true = match_with_pattern(
-                        std::str::from_utf8(input_bytes_1).unwrap(),
-                        &pattern_1
-                    ) {
-                        input_bytes = input_bytes_1;
-                    } else if let true = match_with_pattern(
-                        std::str::from_utf8(input_bytes_2).unwrap(),
-                        &pattern_2,
-                    ) {
-                        input_bytes = input_bytes_2;
-                    } else {
-                        return false;
-                    }
+            // Unanchored search: a new attempt can start at every position.
+            if pos < input.len() {
+                for &start in starts {
+                    add_thread(program, &mut nlist, &mut seen, start, pos + 1, input.len());
                 }
             }
+
+            clist = nlist;
+        }
+
+        let mut ids: Vec<usize> = matched
+            .iter()
+            .enumerate()
+            .filter(|(_, &m)| m)
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn matches_literal_substring() {
+            assert!(match_pattern("hello world", "world"));
+            assert!(!match_pattern("hello world", "bye"));
+        }
+
+        #[test]
+        fn anchors_restrict_the_match_position() {
+            assert!(match_pattern("abc", "^abc$"));
+            assert!(!match_pattern("xabc", "^abc$"));
+            assert!(!match_pattern("abcx", "^abc$"));
+            assert!(match_pattern("abcx", "^abc"));
+            assert!(match_pattern("xabc", "abc$"));
+        }
+
+        #[test]
+        fn alternation_composes_with_a_trailing_pattern() {
+            // Regression: the old recursive matcher discarded whatever came
+            // after an `(a|b)` group, so "s" after the group was never checked.
+            assert!(match_pattern("cats", "(cat|dog)s"));
+            assert!(match_pattern("dogs", "(cat|dog)s"));
+            assert!(!match_pattern("fish", "(cat|dog)s"));
+        }
+
+        #[test]
+        fn anchors_combine_with_alternation() {
+            assert!(match_pattern("cat", "^(cat|dog)$"));
+            assert!(!match_pattern("scat", "^(cat|dog)$"));
+            assert!(!match_pattern("cats", "^(cat|dog)$"));
+        }
+
+        #[test]
+        fn plus_requires_at_least_one() {
+            assert!(match_pattern("aaa", "a+"));
+            assert!(match_pattern("a", "a+"));
+            assert!(!match_pattern("", "a+"));
+        }
+
+        #[test]
+        fn star_allows_zero() {
+            assert!(match_pattern("aaa", "a*"));
+            assert!(match_pattern("", "a*"));
+        }
+
+        #[test]
+        fn zero_or_one_is_optional() {
+            assert!(match_pattern("color", "colou?r"));
+            assert!(match_pattern("colour", "colou?r"));
+            assert!(!match_pattern("colouur", "^colou?r$"));
+        }
+
+        #[test]
+        fn repeat_exact_count() {
+            assert!(match_pattern("aa", "^a{2}$"));
+            assert!(!match_pattern("a", "^a{2}$"));
+            assert!(!match_pattern("aaa", "^a{2}$"));
+        }
+
+        #[test]
+        fn repeat_at_least() {
+            assert!(!match_pattern("a", "^a{2,}$"));
+            assert!(match_pattern("aa", "^a{2,}$"));
+            assert!(match_pattern("aaaaaa", "^a{2,}$"));
+        }
+
+        #[test]
+        fn repeat_bounded_range() {
+            assert!(!match_pattern("a", "^a{2,3}$"));
+            assert!(match_pattern("aa", "^a{2,3}$"));
+            assert!(match_pattern("aaa", "^a{2,3}$"));
+            assert!(!match_pattern("aaaa", "^a{2,3}$"));
+        }
+
+        #[test]
+        fn malformed_brace_falls_back_to_a_literal() {
+            assert!(match_pattern("a{x", "a{x"));
+            assert!(match_pattern("a{2", "a{2"));
+        }
+
+        #[test]
+        fn match_any_reports_every_matching_pattern() {
+            let mut matched = match_any("dog", &["cat", "dog", "bird"]);
+            matched.sort_unstable();
+            assert_eq!(matched, vec![1]);
+
+            let mut matched = match_any("catdog", &["cat", "dog", "bird"]);
+            matched.sort_unstable();
+            assert_eq!(matched, vec![0, 1]);
+
+            assert!(match_any("fish", &["cat", "dog"]).is_empty());
+        }
+
+        #[test]
+        fn match_tokens_requires_a_full_string_match() {
+            let tokens = vec![RegexPattern::Star(Box::new(RegexPattern::NegativeCharSet(vec!['/'])))];
+            assert!(match_tokens("notes", &tokens));
+            assert!(!match_tokens("a/b", &tokens));
+        }
+
+        #[test]
+        fn hex_escape_matches_a_literal_byte() {
+            assert!(match_pattern_bytes(&[0x41], r"\x41"));
+            assert!(!match_pattern_bytes(&[0x42], r"\x41"));
+            assert!(match_pattern_bytes(b"a\xffb", r"a\xffb"));
+        }
+
+        #[test]
+        fn byte_mode_supports_classes_dot_digit_and_word() {
+            assert!(match_pattern_bytes(b"cat", "c.t"));
+            assert!(match_pattern_bytes(b"5", r"\d"));
+            assert!(!match_pattern_bytes(b"x", r"\d"));
+            assert!(match_pattern_bytes(b"_", r"\w"));
+            assert!(match_pattern_bytes(b"banana", "[abn]+"));
+            assert!(!match_pattern_bytes(b"xyz", "[abn]+"));
+        }
+
+        #[test]
+        fn byte_mode_handles_input_that_is_not_valid_utf8() {
+            // 0xff is never valid as a standalone UTF-8 byte, so this input
+            // is not a valid &str and cannot be passed to match_pattern at
+            // all; match_pattern_bytes works directly on the raw bytes instead.
+            let input = [b'a', 0xff, b'b'];
+            assert!(match_pattern_bytes(&input, r"a\xffb"));
+            assert!(match_pattern_bytes(&input, "a.b"));
         }
-        true
     }
 }